@@ -0,0 +1,399 @@
+//! Async counterpart of `Actor`, for command handlers that need to do I/O (database reads,
+//! external calls) while handling a `Cause`.
+//!
+//! Only `handle`/`apply` are async here: persisting and replaying `Effects`, broadcasting to
+//! `Projections`, and snapshotting aren't actually I/O-bound for the stores this crate ships, so
+//! `AsyncDispatcher`/`AsyncSnapshottingDispatcher` reuse `EventStore`, `Projection`,
+//! `SnapshotStore`, and the generation-batching helper from [`crate::dispatch`] instead of
+//! re-deriving them. The surrounding reconstitute→handle→append control flow is still duplicated
+//! from [`crate::dispatch`] (see the TODOs on `execute` below) since today's `async fn` in traits
+//! doesn't give us a way to share it across sync and async `Actor`s. Requires the `async` feature.
+
+use std::error::Error;
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+use crate::dispatch::{batches_by_generation, SharedProjection};
+use crate::snapshot::{Generational, SnapshotPolicy, SnapshotStore};
+use crate::store::EventStore;
+use crate::{Cause, Effect};
+
+/// Async counterpart of `Actor`, for `Actors` whose `handle`/`apply` need to do I/O.
+#[allow(async_fn_in_trait)]
+pub trait AsyncActor<C: Cause, E: Effect, Err: Error> {
+    /// Unique Id for `Actor`.
+    type Id;
+    /// Version of `Actor` dependent on `Effects` applied.
+    type Version;
+    /// Handle `Cause` returning vector of `Effects` or error.
+    async fn handle(&self, cause: C) -> Result<Vec<E>, Err>;
+    /// Apply `Effects` on Actor.
+    async fn apply(&mut self, effects: Vec<E>) -> Result<(), Err>;
+}
+
+/// Like `Dispatcher`, but reconstitutes an `AsyncActor` and awaits its `handle`/`apply`.
+///
+/// `execute` performs the same cycle as `Dispatcher::execute`: load prior `Effects`, rebuild the
+/// `Actor` via `apply`, `handle` the `Cause` to produce new `Effects`, then `append` them back to
+/// the store under optimistic concurrency. On a successful append, every committed `Effect` is
+/// broadcast to the registered `Projections` so read models can be kept in sync.
+pub struct AsyncDispatcher<A, Id, E: Effect, S> {
+    store: S,
+    projections: Mutex<Vec<SharedProjection<E, Id>>>,
+    actor: PhantomData<A>,
+}
+
+impl<A, Id, E: Effect, S> AsyncDispatcher<A, Id, E, S> {
+    /// Create an `AsyncDispatcher` backed by `store` with no registered `Projections`.
+    pub fn new(store: S) -> Self {
+        AsyncDispatcher {
+            store,
+            projections: Mutex::new(Vec::new()),
+            actor: PhantomData,
+        }
+    }
+
+    /// Register a `Projection` to receive every `Effect` this `AsyncDispatcher` successfully
+    /// appends.
+    ///
+    /// Callers keep their own `Arc` handle to query the `Projection` later.
+    pub fn register_projection(&self, projection: SharedProjection<E, Id>) {
+        self.projections.lock().unwrap().push(projection);
+    }
+}
+
+impl<A, Id, E: Effect, S> AsyncDispatcher<A, Id, E, S> {
+    /// Reconstitute the `AsyncActor` addressed by `cause`, `handle` it, and `append` the new
+    /// `Effects`.
+    ///
+    /// TODO: this mirrors `Dispatcher::execute`'s control flow step for step, differing only in
+    /// the `.await`s `AsyncActor` needs. There's no blocking issue today, but revisit sharing the
+    /// two once `async fn` in traits supports the generic dispatch this crate would need to call
+    /// through either an `Actor` or an `AsyncActor` uniformly.
+    pub async fn execute<C, Err>(&self, cause: C) -> Result<Vec<E>, Err>
+    where
+        A: AsyncActor<C, E, Err> + Default,
+        C: Cause<ActorId = Id>,
+        Id: Clone,
+        E: Effect<Version = C::ActorVersion> + Clone,
+        Err: Error + From<S::Err>,
+        S: EventStore<Id, E>,
+    {
+        let actor_id = cause.actor_id();
+
+        let history = self.store.load(actor_id.clone())?;
+        let mut actor = A::default();
+        actor
+            .apply(history.into_iter().map(|envelope| envelope.effect).collect())
+            .await?;
+
+        let before_generation = self.store.generation(actor_id.clone())?;
+        let expected_version = cause.actor_version();
+        let effects = actor.handle(cause).await?;
+
+        self.store
+            .append(actor_id.clone(), expected_version, effects.clone())?;
+
+        let committed = self.store.load_since(actor_id.clone(), before_generation)?;
+        let projections = self.projections.lock().unwrap();
+        for envelope in &committed {
+            for projection in projections.iter() {
+                projection.lock().unwrap().project(envelope);
+            }
+        }
+
+        Ok(effects)
+    }
+}
+
+/// Like `AsyncDispatcher`, but reconstitutes an `AsyncActor` from its latest `Snapshot` plus only
+/// the `Effects` committed since, instead of replaying its entire history.
+pub struct AsyncSnapshottingDispatcher<A, Id, E: Effect, S, Sn> {
+    store: S,
+    snapshots: Sn,
+    policy: SnapshotPolicy,
+    projections: Mutex<Vec<SharedProjection<E, Id>>>,
+    actor: PhantomData<A>,
+}
+
+impl<A, Id, E: Effect, S, Sn> AsyncSnapshottingDispatcher<A, Id, E, S, Sn> {
+    /// Create an `AsyncSnapshottingDispatcher` backed by `store` and `snapshots`, snapshotting per
+    /// `policy`.
+    pub fn new(store: S, snapshots: Sn, policy: SnapshotPolicy) -> Self {
+        AsyncSnapshottingDispatcher {
+            store,
+            snapshots,
+            policy,
+            projections: Mutex::new(Vec::new()),
+            actor: PhantomData,
+        }
+    }
+
+    /// Register a `Projection` to receive every `Effect` this dispatcher successfully appends.
+    pub fn register_projection(&self, projection: SharedProjection<E, Id>) {
+        self.projections.lock().unwrap().push(projection);
+    }
+}
+
+impl<A, Id, E: Effect, S, Sn> AsyncSnapshottingDispatcher<A, Id, E, S, Sn> {
+    /// Reconstitute the `AsyncActor` from its latest `Snapshot` and any `Effects` committed
+    /// since, `handle` `cause`, `append` the new `Effects`, and snapshot again once `policy`
+    /// allows it.
+    ///
+    /// TODO: same duplication with `SnapshottingDispatcher::execute` noted on
+    /// `AsyncDispatcher::execute` above — tracked there, not repeated per call site.
+    pub async fn execute<C, Err>(&self, cause: C) -> Result<Vec<E>, Err>
+    where
+        A: AsyncActor<C, E, Err> + Generational + Clone + Default,
+        C: Cause<ActorId = Id>,
+        Id: Clone,
+        E: Effect<Version = C::ActorVersion> + Clone,
+        Err: Error + From<S::Err> + From<Sn::Err>,
+        S: EventStore<Id, E>,
+        Sn: SnapshotStore<Id, A>,
+    {
+        let actor_id = cause.actor_id();
+
+        let (mut actor, since_generation) = match self.snapshots.load(&actor_id)? {
+            Some((actor, generation)) => (actor, generation),
+            None => (A::default(), 0),
+        };
+
+        let history = self.store.load_since(actor_id.clone(), since_generation)?;
+        for batch in batches_by_generation(&history) {
+            actor.apply(batch).await?;
+        }
+
+        let before_generation = self.store.generation(actor_id.clone())?;
+        let expected_version = cause.actor_version();
+        let effects = actor.handle(cause).await?;
+
+        self.store
+            .append(actor_id.clone(), expected_version, effects.clone())?;
+
+        let committed = self.store.load_since(actor_id.clone(), before_generation)?;
+        for batch in batches_by_generation(&committed) {
+            actor.apply(batch).await?;
+        }
+
+        let generation = actor.generation();
+        if self.policy.should_snapshot(since_generation, generation) {
+            self.snapshots.save(actor_id.clone(), actor.clone(), generation)?;
+        }
+
+        let projections = self.projections.lock().unwrap();
+        for envelope in &committed {
+            for projection in projections.iter() {
+                projection.lock().unwrap().project(envelope);
+            }
+        }
+
+        Ok(effects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{InMemoryViewStore, View};
+    use crate::snapshot::InMemorySnapshotStore;
+    use crate::store::{InMemoryEventStore, StoreError};
+    use std::fmt;
+    use std::sync::Arc;
+
+    #[derive(Debug)]
+    struct DoorError(String);
+    impl fmt::Display for DoorError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+    impl Error for DoorError {}
+    impl From<StoreError<u8>> for DoorError {
+        fn from(err: StoreError<u8>) -> Self {
+            DoorError(err.to_string())
+        }
+    }
+    impl From<std::convert::Infallible> for DoorError {
+        fn from(err: std::convert::Infallible) -> Self {
+            match err {}
+        }
+    }
+
+    #[derive(Clone)]
+    struct Opened {
+        version: u8,
+        key: u32,
+    }
+    impl Effect for Opened {
+        type Version = u8;
+        type Key = u32;
+        fn version(&self) -> Self::Version {
+            self.version
+        }
+        fn key(&self) -> Self::Key {
+            self.key
+        }
+    }
+
+    struct Open {
+        actor_id: u32,
+        actor_version: u8,
+    }
+    impl Cause for Open {
+        type ActorId = u32;
+        type ActorVersion = u8;
+        fn actor_id(&self) -> Self::ActorId {
+            self.actor_id
+        }
+        fn actor_version(&self) -> Self::ActorVersion {
+            self.actor_version
+        }
+    }
+
+    #[derive(Default, Clone)]
+    struct Door {
+        version: u8,
+        generation: u64,
+    }
+    impl AsyncActor<Open, Opened, DoorError> for Door {
+        type Id = u32;
+        type Version = u8;
+        async fn handle(&self, _cause: Open) -> Result<Vec<Opened>, DoorError> {
+            Ok(vec![Opened {
+                version: self.version + 1,
+                key: (self.version + 1) as u32,
+            }])
+        }
+        async fn apply(&mut self, effects: Vec<Opened>) -> Result<(), DoorError> {
+            if let Some(last) = effects.last() {
+                self.version = last.version;
+                self.generation += 1;
+            }
+            Ok(())
+        }
+    }
+    impl Generational for Door {
+        fn generation(&self) -> u64 {
+            self.generation
+        }
+    }
+
+    #[test]
+    fn execute_handles_and_persists_effects() {
+        let dispatcher: AsyncDispatcher<Door, u32, Opened, InMemoryEventStore<u32, Opened>> =
+            AsyncDispatcher::new(InMemoryEventStore::new());
+
+        let effects = pollster::block_on(dispatcher.execute(Open {
+            actor_id: 1,
+            actor_version: 0,
+        }))
+        .unwrap();
+
+        assert_eq!(effects.len(), 1);
+        assert_eq!(dispatcher.store.load(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn execute_rejects_stale_expected_version() {
+        let dispatcher: AsyncDispatcher<Door, u32, Opened, InMemoryEventStore<u32, Opened>> =
+            AsyncDispatcher::new(InMemoryEventStore::new());
+
+        pollster::block_on(dispatcher.execute(Open {
+            actor_id: 1,
+            actor_version: 0,
+        }))
+        .unwrap();
+
+        let result = pollster::block_on(dispatcher.execute(Open {
+            actor_id: 1,
+            actor_version: 0,
+        }));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn execute_broadcasts_committed_effects_to_projections() {
+        let dispatcher: AsyncDispatcher<Door, u32, Opened, InMemoryEventStore<u32, Opened>> =
+            AsyncDispatcher::new(InMemoryEventStore::new());
+        let views: Arc<Mutex<InMemoryViewStore<u32, OpenCount>>> =
+            Arc::new(Mutex::new(InMemoryViewStore::new()));
+        dispatcher.register_projection(views.clone());
+
+        pollster::block_on(dispatcher.execute(Open {
+            actor_id: 1,
+            actor_version: 0,
+        }))
+        .unwrap();
+
+        assert_eq!(views.lock().unwrap().view(&1).unwrap().count, 1);
+    }
+
+    #[derive(Clone, Default)]
+    struct OpenCount {
+        count: u32,
+    }
+    impl View<Opened> for OpenCount {
+        fn apply(&mut self, _effect: &Opened) {
+            self.count += 1;
+        }
+    }
+
+    type SharedSnapshots = Arc<InMemorySnapshotStore<u32, Door>>;
+
+    #[test]
+    fn snapshotting_execute_reconstitutes_from_snapshot_plus_delta_effects() {
+        let dispatcher: AsyncSnapshottingDispatcher<
+            Door,
+            u32,
+            Opened,
+            InMemoryEventStore<u32, Opened>,
+            InMemorySnapshotStore<u32, Door>,
+        > = AsyncSnapshottingDispatcher::new(
+            InMemoryEventStore::new(),
+            InMemorySnapshotStore::new(),
+            SnapshotPolicy::every(1),
+        );
+
+        pollster::block_on(dispatcher.execute(Open {
+            actor_id: 1,
+            actor_version: 0,
+        }))
+        .unwrap();
+        let effects = pollster::block_on(dispatcher.execute(Open {
+            actor_id: 1,
+            actor_version: 1,
+        }))
+        .unwrap();
+
+        assert_eq!(effects[0].version, 2);
+    }
+
+    #[test]
+    fn snapshotting_execute_tracks_generation_across_several_unsnapshotted_commits() {
+        let snapshots: SharedSnapshots = Arc::new(InMemorySnapshotStore::new());
+        let store: InMemoryEventStore<u32, Opened> = InMemoryEventStore::new();
+        let dispatcher: AsyncSnapshottingDispatcher<
+            Door,
+            u32,
+            Opened,
+            InMemoryEventStore<u32, Opened>,
+            SharedSnapshots,
+        > = AsyncSnapshottingDispatcher::new(store, snapshots.clone(), SnapshotPolicy::every(3));
+
+        for version in 0..6 {
+            pollster::block_on(dispatcher.execute(Open {
+                actor_id: 1,
+                actor_version: version,
+            }))
+            .unwrap();
+        }
+
+        let store_generation = dispatcher.store.generation(1).unwrap();
+        assert_eq!(store_generation, 6);
+
+        let snapshot = snapshots.load(&1).unwrap();
+        assert_eq!(snapshot.unwrap().1, store_generation);
+    }
+}
@@ -0,0 +1,284 @@
+//! Compositional combination of two `Actors` into one aggregate.
+//!
+//! `Combined<A1, A2>` lets a larger aggregate be assembled from small, independently-tested
+//! `Actor` implementations instead of hand-writing a `Cause`/`Effect` enum and routing `handle`
+//! and `apply` to the right half by hand.
+
+use std::error::Error;
+
+use crate::{Actor, Cause, Effect};
+
+/// A `Cause` or `Effect` belonging to one of the two `Actors` a `Combined` merges.
+pub enum Either<L, R> {
+    /// Belongs to the first (`A1`) `Actor`.
+    Left(L),
+    /// Belongs to the second (`A2`) `Actor`.
+    Right(R),
+}
+
+impl<C1, C2> Cause for Either<C1, C2>
+where
+    C1: Cause,
+    C2: Cause<ActorId = C1::ActorId, ActorVersion = C1::ActorVersion>,
+{
+    type ActorId = C1::ActorId;
+    type ActorVersion = C1::ActorVersion;
+    fn actor_id(&self) -> Self::ActorId {
+        match self {
+            Either::Left(cause) => cause.actor_id(),
+            Either::Right(cause) => cause.actor_id(),
+        }
+    }
+    fn actor_version(&self) -> Self::ActorVersion {
+        match self {
+            Either::Left(cause) => cause.actor_version(),
+            Either::Right(cause) => cause.actor_version(),
+        }
+    }
+}
+
+impl<E1, E2> Effect for Either<E1, E2>
+where
+    E1: Effect,
+    E2: Effect<Version = E1::Version, Key = E1::Key>,
+{
+    type Version = E1::Version;
+    type Key = E1::Key;
+    fn version(&self) -> Self::Version {
+        match self {
+            Either::Left(effect) => effect.version(),
+            Either::Right(effect) => effect.version(),
+        }
+    }
+    fn key(&self) -> Self::Key {
+        match self {
+            Either::Left(effect) => effect.key(),
+            Either::Right(effect) => effect.key(),
+        }
+    }
+}
+
+/// Merges `Actors` `A1` and `A2` into a single `Actor` over the sum of their `Cause`/`Effect`
+/// types.
+pub struct Combined<A1, A2> {
+    /// First inner `Actor`.
+    pub first: A1,
+    /// Second inner `Actor`.
+    pub second: A2,
+}
+
+impl<A1, A2> Combined<A1, A2> {
+    /// Combine `first` and `second` into one `Actor`.
+    pub fn new(first: A1, second: A2) -> Self {
+        Combined { first, second }
+    }
+}
+
+impl<A1: Default, A2: Default> Default for Combined<A1, A2> {
+    fn default() -> Self {
+        Combined {
+            first: A1::default(),
+            second: A2::default(),
+        }
+    }
+}
+
+impl<A1, A2, C1, C2, E1, E2, Err> Actor<Either<C1, C2>, Either<E1, E2>, Err> for Combined<A1, A2>
+where
+    A1: Actor<C1, E1, Err>,
+    A2: Actor<C2, E2, Err>,
+    C1: Cause,
+    C2: Cause<ActorId = C1::ActorId, ActorVersion = C1::ActorVersion>,
+    E1: Effect,
+    E2: Effect<Version = E1::Version, Key = E1::Key>,
+    Err: Error,
+{
+    /// Product of the inner `Actors`' ids.
+    type Id = (A1::Id, A2::Id);
+    /// Product of the inner `Actors`' versions.
+    type Version = (A1::Version, A2::Version);
+
+    fn handle(&self, cause: Either<C1, C2>) -> Result<Vec<Either<E1, E2>>, Err> {
+        match cause {
+            Either::Left(cause) => Ok(self
+                .first
+                .handle(cause)?
+                .into_iter()
+                .map(Either::Left)
+                .collect()),
+            Either::Right(cause) => Ok(self
+                .second
+                .handle(cause)?
+                .into_iter()
+                .map(Either::Right)
+                .collect()),
+        }
+    }
+
+    fn apply(&mut self, effects: Vec<Either<E1, E2>>) -> Result<(), Err> {
+        let mut first_effects = Vec::new();
+        let mut second_effects = Vec::new();
+        for effect in effects {
+            match effect {
+                Either::Left(effect) => first_effects.push(effect),
+                Either::Right(effect) => second_effects.push(effect),
+            }
+        }
+
+        if !first_effects.is_empty() {
+            self.first.apply(first_effects)?;
+        }
+        if !second_effects.is_empty() {
+            self.second.apply(second_effects)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simple_error::SimpleError;
+
+    struct Open {
+        actor_id: u32,
+        actor_version: u8,
+    }
+    impl Cause for Open {
+        type ActorId = u32;
+        type ActorVersion = u8;
+        fn actor_id(&self) -> Self::ActorId {
+            self.actor_id
+        }
+        fn actor_version(&self) -> Self::ActorVersion {
+            self.actor_version
+        }
+    }
+
+    struct Close {
+        actor_id: u32,
+        actor_version: u8,
+    }
+    impl Cause for Close {
+        type ActorId = u32;
+        type ActorVersion = u8;
+        fn actor_id(&self) -> Self::ActorId {
+            self.actor_id
+        }
+        fn actor_version(&self) -> Self::ActorVersion {
+            self.actor_version
+        }
+    }
+
+    struct Opened {
+        key: u32,
+    }
+    impl Effect for Opened {
+        type Version = u8;
+        type Key = u32;
+        fn version(&self) -> Self::Version {
+            1
+        }
+        fn key(&self) -> Self::Key {
+            self.key
+        }
+    }
+
+    struct Closed {
+        key: u32,
+    }
+    impl Effect for Closed {
+        type Version = u8;
+        type Key = u32;
+        fn version(&self) -> Self::Version {
+            1
+        }
+        fn key(&self) -> Self::Key {
+            self.key
+        }
+    }
+
+    #[derive(Default)]
+    struct Door {
+        open: bool,
+    }
+    impl Actor<Open, Opened, SimpleError> for Door {
+        type Id = u32;
+        type Version = u8;
+        fn handle(&self, cause: Open) -> Result<Vec<Opened>, SimpleError> {
+            Ok(vec![Opened {
+                key: cause.actor_id,
+            }])
+        }
+        fn apply(&mut self, effects: Vec<Opened>) -> Result<(), SimpleError> {
+            if !effects.is_empty() {
+                self.open = true;
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct Lock {
+        locked: bool,
+    }
+    impl Actor<Close, Closed, SimpleError> for Lock {
+        type Id = u32;
+        type Version = u8;
+        fn handle(&self, cause: Close) -> Result<Vec<Closed>, SimpleError> {
+            Ok(vec![Closed {
+                key: cause.actor_id,
+            }])
+        }
+        fn apply(&mut self, effects: Vec<Closed>) -> Result<(), SimpleError> {
+            if !effects.is_empty() {
+                self.locked = true;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn handle_routes_left_cause_to_first_actor() {
+        let combined: Combined<Door, Lock> = Combined::default();
+
+        let effects = combined
+            .handle(Either::Left(Open {
+                actor_id: 1,
+                actor_version: 0,
+            }))
+            .unwrap();
+
+        assert!(matches!(effects[0], Either::Left(_)));
+    }
+
+    #[test]
+    fn handle_routes_right_cause_to_second_actor() {
+        let combined: Combined<Door, Lock> = Combined::default();
+
+        let effects = combined
+            .handle(Either::Right(Close {
+                actor_id: 1,
+                actor_version: 0,
+            }))
+            .unwrap();
+
+        assert!(matches!(effects[0], Either::Right(_)));
+    }
+
+    #[test]
+    fn apply_dispatches_each_effect_to_its_owning_actor() {
+        let mut combined: Combined<Door, Lock> = Combined::default();
+
+        combined
+            .apply(vec![
+                Either::Left(Opened { key: 1 }),
+                Either::Right(Closed { key: 1 }),
+            ])
+            .unwrap();
+
+        assert!(combined.first.open);
+        assert!(combined.second.locked);
+    }
+}
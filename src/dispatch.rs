@@ -0,0 +1,429 @@
+//! Runtime that reconstitutes an `Actor` from its persisted `Effects` and executes a `Cause`
+//! against it end to end.
+
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+use crate::envelope::EventEnvelope;
+use crate::query::Projection;
+use crate::snapshot::{Generational, SnapshotPolicy, SnapshotStore};
+use crate::store::EventStore;
+use crate::{Actor, Cause, Effect};
+
+/// Shared handle to a `Projection`, registered with a `Dispatcher` to receive committed `Effects`.
+pub(crate) type SharedProjection<E, Id> = Arc<Mutex<dyn Projection<Id, E> + Send>>;
+
+/// Loads an `Actor`'s history from an `EventStore`, hands it a `Cause`, and persists the result.
+///
+/// `execute` performs the full cycle: load prior `Effects`, rebuild the `Actor` via `apply`,
+/// `handle` the `Cause` to produce new `Effects`, then `append` them back to the store under
+/// optimistic concurrency. On a successful append, every committed `Effect` is broadcast to the
+/// registered `Projections` so read models can be kept in sync.
+pub struct Dispatcher<A, Id, E: Effect, S> {
+    store: S,
+    projections: Mutex<Vec<SharedProjection<E, Id>>>,
+    actor: PhantomData<A>,
+}
+
+impl<A, Id, E: Effect, S> Dispatcher<A, Id, E, S> {
+    /// Create a `Dispatcher` backed by `store` with no registered `Projections`.
+    pub fn new(store: S) -> Self {
+        Dispatcher {
+            store,
+            projections: Mutex::new(Vec::new()),
+            actor: PhantomData,
+        }
+    }
+
+    /// Register a `Projection` to receive every `Effect` this `Dispatcher` successfully appends.
+    ///
+    /// Callers keep their own `Arc` handle to query the `Projection` later.
+    pub fn register_projection(&self, projection: SharedProjection<E, Id>) {
+        self.projections.lock().unwrap().push(projection);
+    }
+}
+
+impl<A, Id, E: Effect, S> Dispatcher<A, Id, E, S> {
+    /// Reconstitute the `Actor` addressed by `cause`, `handle` it, and `append` the new `Effects`.
+    pub fn execute<C, Err>(&self, cause: C) -> Result<Vec<E>, Err>
+    where
+        A: Actor<C, E, Err> + Default,
+        C: Cause<ActorId = Id>,
+        Id: Clone,
+        E: Effect<Version = C::ActorVersion> + Clone,
+        Err: std::error::Error + From<S::Err>,
+        S: EventStore<Id, E>,
+    {
+        let actor_id = cause.actor_id();
+
+        let history = self.store.load(actor_id.clone())?;
+        let mut actor = A::default();
+        actor.apply(history.into_iter().map(|envelope| envelope.effect).collect())?;
+
+        let before_generation = self.store.generation(actor_id.clone())?;
+        let expected_version = cause.actor_version();
+        let effects = actor.handle(cause)?;
+
+        self.store
+            .append(actor_id.clone(), expected_version, effects.clone())?;
+
+        let committed = self.store.load_since(actor_id.clone(), before_generation)?;
+        let projections = self.projections.lock().unwrap();
+        for envelope in &committed {
+            for projection in projections.iter() {
+                projection.lock().unwrap().project(envelope);
+            }
+        }
+
+        Ok(effects)
+    }
+}
+
+/// Group `envelopes` into the consecutive runs committed in the same `EventStore` generation,
+/// in commit order, so each run can be handed to `Actor::apply` as its own batch.
+///
+/// `Generational::generation` is only meaningful if `apply` is called once per committed
+/// generation: flattening several generations into a single `apply` call would undercount it.
+pub(crate) fn batches_by_generation<Id, E: Effect + Clone>(
+    envelopes: &[EventEnvelope<Id, E>],
+) -> Vec<Vec<E>> {
+    let mut batches: Vec<Vec<E>> = Vec::new();
+    let mut current_generation = None;
+    for envelope in envelopes {
+        if current_generation != Some(envelope.generation) {
+            batches.push(Vec::new());
+            current_generation = Some(envelope.generation);
+        }
+        batches.last_mut().unwrap().push(envelope.effect.clone());
+    }
+    batches
+}
+
+/// Like `Dispatcher`, but reconstitutes an `Actor` from its latest `Snapshot` plus only the
+/// `Effects` committed since, instead of replaying its entire history.
+pub struct SnapshottingDispatcher<A, Id, E: Effect, S, Sn> {
+    store: S,
+    snapshots: Sn,
+    policy: SnapshotPolicy,
+    projections: Mutex<Vec<SharedProjection<E, Id>>>,
+    actor: PhantomData<A>,
+}
+
+impl<A, Id, E: Effect, S, Sn> SnapshottingDispatcher<A, Id, E, S, Sn> {
+    /// Create a `SnapshottingDispatcher` backed by `store` and `snapshots`, snapshotting per `policy`.
+    pub fn new(store: S, snapshots: Sn, policy: SnapshotPolicy) -> Self {
+        SnapshottingDispatcher {
+            store,
+            snapshots,
+            policy,
+            projections: Mutex::new(Vec::new()),
+            actor: PhantomData,
+        }
+    }
+
+    /// Register a `Projection` to receive every `Effect` this dispatcher successfully appends.
+    pub fn register_projection(&self, projection: SharedProjection<E, Id>) {
+        self.projections.lock().unwrap().push(projection);
+    }
+}
+
+impl<A, Id, E: Effect, S, Sn> SnapshottingDispatcher<A, Id, E, S, Sn> {
+    /// Reconstitute the `Actor` from its latest `Snapshot` and any `Effects` committed since,
+    /// `handle` `cause`, `append` the new `Effects`, and snapshot again once `policy` allows it.
+    pub fn execute<C, Err>(&self, cause: C) -> Result<Vec<E>, Err>
+    where
+        A: Actor<C, E, Err> + Generational + Clone + Default,
+        C: Cause<ActorId = Id>,
+        Id: Clone,
+        E: Effect<Version = C::ActorVersion> + Clone,
+        Err: std::error::Error + From<S::Err> + From<Sn::Err>,
+        S: EventStore<Id, E>,
+        Sn: SnapshotStore<Id, A>,
+    {
+        let actor_id = cause.actor_id();
+
+        let (mut actor, since_generation) = match self.snapshots.load(&actor_id)? {
+            Some((actor, generation)) => (actor, generation),
+            None => (A::default(), 0),
+        };
+
+        let history = self.store.load_since(actor_id.clone(), since_generation)?;
+        for batch in batches_by_generation(&history) {
+            actor.apply(batch)?;
+        }
+
+        let before_generation = self.store.generation(actor_id.clone())?;
+        let expected_version = cause.actor_version();
+        let effects = actor.handle(cause)?;
+
+        self.store
+            .append(actor_id.clone(), expected_version, effects.clone())?;
+
+        let committed = self.store.load_since(actor_id.clone(), before_generation)?;
+        for batch in batches_by_generation(&committed) {
+            actor.apply(batch)?;
+        }
+
+        let generation = actor.generation();
+        if self.policy.should_snapshot(since_generation, generation) {
+            self.snapshots.save(actor_id.clone(), actor.clone(), generation)?;
+        }
+
+        let projections = self.projections.lock().unwrap();
+        for envelope in &committed {
+            for projection in projections.iter() {
+                projection.lock().unwrap().project(envelope);
+            }
+        }
+
+        Ok(effects)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{InMemoryViewStore, View};
+    use crate::snapshot::InMemorySnapshotStore;
+    use crate::store::{InMemoryEventStore, StoreError};
+    use std::fmt;
+
+    #[derive(Debug)]
+    struct DoorError(String);
+    impl fmt::Display for DoorError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+    impl std::error::Error for DoorError {}
+    impl From<StoreError<u8>> for DoorError {
+        fn from(err: StoreError<u8>) -> Self {
+            DoorError(err.to_string())
+        }
+    }
+    impl From<std::convert::Infallible> for DoorError {
+        fn from(err: std::convert::Infallible) -> Self {
+            match err {}
+        }
+    }
+
+    #[derive(Clone)]
+    struct Opened {
+        version: u8,
+        key: u32,
+    }
+    impl Effect for Opened {
+        type Version = u8;
+        type Key = u32;
+        fn version(&self) -> Self::Version {
+            self.version
+        }
+        fn key(&self) -> Self::Key {
+            self.key
+        }
+    }
+
+    struct Open {
+        actor_id: u32,
+        actor_version: u8,
+    }
+    impl Cause for Open {
+        type ActorId = u32;
+        type ActorVersion = u8;
+        fn actor_id(&self) -> Self::ActorId {
+            self.actor_id
+        }
+        fn actor_version(&self) -> Self::ActorVersion {
+            self.actor_version
+        }
+    }
+
+    #[derive(Default, Clone)]
+    struct Door {
+        version: u8,
+        generation: u64,
+    }
+    impl Actor<Open, Opened, DoorError> for Door {
+        type Id = u32;
+        type Version = u8;
+        fn handle(&self, _cause: Open) -> Result<Vec<Opened>, DoorError> {
+            Ok(vec![Opened {
+                version: self.version + 1,
+                key: (self.version + 1) as u32,
+            }])
+        }
+        fn apply(&mut self, effects: Vec<Opened>) -> Result<(), DoorError> {
+            if let Some(last) = effects.last() {
+                self.version = last.version;
+                self.generation += 1;
+            }
+            Ok(())
+        }
+    }
+    impl Generational for Door {
+        fn generation(&self) -> u64 {
+            self.generation
+        }
+    }
+
+    #[test]
+    fn execute_handles_and_persists_effects() {
+        let dispatcher: Dispatcher<Door, u32, Opened, InMemoryEventStore<u32, Opened>> =
+            Dispatcher::new(InMemoryEventStore::new());
+
+        let effects = dispatcher
+            .execute(Open {
+                actor_id: 1,
+                actor_version: 0,
+            })
+            .unwrap();
+
+        assert_eq!(effects.len(), 1);
+        assert_eq!(dispatcher.store.load(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn execute_rejects_stale_expected_version() {
+        let dispatcher: Dispatcher<Door, u32, Opened, InMemoryEventStore<u32, Opened>> =
+            Dispatcher::new(InMemoryEventStore::new());
+
+        dispatcher
+            .execute(Open {
+                actor_id: 1,
+                actor_version: 0,
+            })
+            .unwrap();
+
+        let result = dispatcher.execute(Open {
+            actor_id: 1,
+            actor_version: 0,
+        });
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn execute_rebuilds_actor_from_history_before_handling() {
+        let dispatcher: Dispatcher<Door, u32, Opened, InMemoryEventStore<u32, Opened>> =
+            Dispatcher::new(InMemoryEventStore::new());
+
+        dispatcher
+            .execute(Open {
+                actor_id: 1,
+                actor_version: 0,
+            })
+            .unwrap();
+        let effects = dispatcher
+            .execute(Open {
+                actor_id: 1,
+                actor_version: 1,
+            })
+            .unwrap();
+
+        assert_eq!(effects[0].version, 2);
+    }
+
+    #[derive(Clone, Default)]
+    struct OpenCount {
+        count: u32,
+    }
+    impl View<Opened> for OpenCount {
+        fn apply(&mut self, _effect: &Opened) {
+            self.count += 1;
+        }
+    }
+
+    #[test]
+    fn execute_broadcasts_committed_effects_to_projections() {
+        let dispatcher: Dispatcher<Door, u32, Opened, InMemoryEventStore<u32, Opened>> =
+            Dispatcher::new(InMemoryEventStore::new());
+        let views: Arc<Mutex<InMemoryViewStore<u32, OpenCount>>> =
+            Arc::new(Mutex::new(InMemoryViewStore::new()));
+        dispatcher.register_projection(views.clone());
+
+        dispatcher
+            .execute(Open {
+                actor_id: 1,
+                actor_version: 0,
+            })
+            .unwrap();
+
+        assert_eq!(views.lock().unwrap().view(&1).unwrap().count, 1);
+    }
+
+    #[test]
+    fn snapshotting_execute_reconstitutes_from_snapshot_plus_delta_effects() {
+        let dispatcher: SnapshottingDispatcher<
+            Door,
+            u32,
+            Opened,
+            InMemoryEventStore<u32, Opened>,
+            InMemorySnapshotStore<u32, Door>,
+        > = SnapshottingDispatcher::new(
+            InMemoryEventStore::new(),
+            InMemorySnapshotStore::new(),
+            SnapshotPolicy::every(1),
+        );
+
+        dispatcher
+            .execute(Open {
+                actor_id: 1,
+                actor_version: 0,
+            })
+            .unwrap();
+        let effects = dispatcher
+            .execute(Open {
+                actor_id: 1,
+                actor_version: 1,
+            })
+            .unwrap();
+
+        assert_eq!(effects[0].version, 2);
+    }
+
+    type SharedSnapshots = Arc<InMemorySnapshotStore<u32, Door>>;
+
+    #[test]
+    fn snapshotting_execute_persists_a_snapshot_per_policy() {
+        let snapshots: SharedSnapshots = Arc::new(InMemorySnapshotStore::new());
+        let dispatcher: SnapshottingDispatcher<Door, u32, Opened, InMemoryEventStore<u32, Opened>, SharedSnapshots> =
+            SnapshottingDispatcher::new(
+                InMemoryEventStore::new(),
+                snapshots.clone(),
+                SnapshotPolicy::every(1),
+            );
+
+        dispatcher
+            .execute(Open {
+                actor_id: 1,
+                actor_version: 0,
+            })
+            .unwrap();
+
+        let snapshot = snapshots.load(&1).unwrap();
+        assert_eq!(snapshot.unwrap().1, 1);
+    }
+
+    #[test]
+    fn snapshotting_execute_tracks_generation_across_several_unsnapshotted_commits() {
+        let snapshots: SharedSnapshots = Arc::new(InMemorySnapshotStore::new());
+        let store: InMemoryEventStore<u32, Opened> = InMemoryEventStore::new();
+        let dispatcher: SnapshottingDispatcher<Door, u32, Opened, InMemoryEventStore<u32, Opened>, SharedSnapshots> =
+            SnapshottingDispatcher::new(store, snapshots.clone(), SnapshotPolicy::every(3));
+
+        for version in 0..6 {
+            dispatcher
+                .execute(Open {
+                    actor_id: 1,
+                    actor_version: version,
+                })
+                .unwrap();
+        }
+
+        let store_generation = dispatcher.store.generation(1).unwrap();
+        assert_eq!(store_generation, 6);
+
+        let snapshot = snapshots.load(&1).unwrap();
+        assert_eq!(snapshot.unwrap().1, store_generation);
+    }
+}
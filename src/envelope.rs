@@ -0,0 +1,109 @@
+//! Metadata-carrying wrapper around a committed `Effect`.
+//!
+//! An `EventEnvelope` is what an `EventStore` actually persists and returns: the `Effect` itself
+//! plus the ordering and correlation metadata a store assigns on commit, so projections and other
+//! downstream consumers aren't limited to what `Effect::version`/`Effect::key` expose.
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::Effect;
+
+/// A committed `Effect` plus store-assigned metadata.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventEnvelope<Id, E: Effect> {
+    /// `Actor` the inner `Effect` was committed for.
+    pub actor_id: Id,
+    /// Monotonic position of this `Effect` within its actor's history, assigned by the store.
+    pub sequence: u64,
+    /// `EventStore` generation the `Effect` was committed as part of.
+    ///
+    /// Shared by every `Effect` appended in the same `EventStore::append` batch, so a replaying
+    /// `Dispatcher` can tell where one committed batch ends and the next begins.
+    pub generation: u64,
+    /// Unix timestamp, in seconds, the `Effect` was committed at.
+    pub timestamp: u64,
+    /// Arbitrary correlation metadata (e.g. causation id, user id) attached at commit time.
+    pub metadata: HashMap<String, String>,
+    /// The committed `Effect`.
+    pub effect: E,
+}
+
+impl<Id, E: Effect> EventEnvelope<Id, E> {
+    /// Wrap `effect` for `actor_id` at `sequence`, timestamped with the current time.
+    ///
+    /// `generation` defaults to `0`; attach the real value with `with_generation`.
+    pub fn new(actor_id: Id, sequence: u64, effect: E) -> Self {
+        EventEnvelope {
+            actor_id,
+            sequence,
+            generation: 0,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            metadata: HashMap::new(),
+            effect,
+        }
+    }
+
+    /// Attach the `EventStore` generation this `Effect` was committed as part of, returning `self`
+    /// for chaining.
+    pub fn with_generation(mut self, generation: u64) -> Self {
+        self.generation = generation;
+        self
+    }
+
+    /// Attach a metadata entry, returning `self` for chaining.
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Opened {
+        key: u32,
+    }
+    impl Effect for Opened {
+        type Version = u8;
+        type Key = u32;
+        fn version(&self) -> Self::Version {
+            1
+        }
+        fn key(&self) -> Self::Key {
+            self.key
+        }
+    }
+
+    #[test]
+    fn new_wraps_effect_with_actor_id_and_sequence() {
+        let envelope = EventEnvelope::new(1_u32, 3, Opened { key: 7 });
+
+        assert_eq!(envelope.actor_id, 1);
+        assert_eq!(envelope.sequence, 3);
+        assert_eq!(envelope.effect.key, 7);
+        assert_eq!(envelope.generation, 0);
+        assert!(envelope.metadata.is_empty());
+    }
+
+    #[test]
+    fn with_generation_attaches_the_commit_generation() {
+        let envelope = EventEnvelope::new(1_u32, 0, Opened { key: 7 }).with_generation(3);
+
+        assert_eq!(envelope.generation, 3);
+    }
+
+    #[test]
+    fn with_metadata_attaches_correlation_data() {
+        let envelope =
+            EventEnvelope::new(1_u32, 0, Opened { key: 7 }).with_metadata("causation_id", "abc");
+
+        assert_eq!(envelope.metadata.get("causation_id").unwrap(), "abc");
+    }
+}
@@ -107,6 +107,15 @@
 //! assert!(result.is_ok());
 //! ```
 
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod combine;
+pub mod dispatch;
+pub mod envelope;
+pub mod query;
+pub mod snapshot;
+pub mod store;
+
 use std::error::Error;
 
 /// Entity that handles `Causes` producing one or more `Effects` upon success.
@@ -200,7 +209,7 @@ mod tests {
             type Id = Id;
             type Version = Version;
             fn handle(&self, command: Command) -> Result<Vec<Event>, SimpleError> {
-                if command.actor_id() == String::from("one") {
+                if command.actor_id() == "one" {
                     return Ok(vec![
                         Event {
                             version: String::from("1.0.0"),
@@ -0,0 +1,119 @@
+//! Read-side projections built from committed `Effects`.
+//!
+//! `Actor` and `EventStore` cover the write side of `Event Sourcing`; a `Projection` builds
+//! denormalized read state from the same `Effects` without the write-side `Actor` knowing
+//! about it.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+use crate::envelope::EventEnvelope;
+use crate::Effect;
+
+/// Builds or updates read state from a committed `Effect`.
+///
+/// Registered with a `Dispatcher`, which calls `project` for every `Effect` it appends.
+pub trait Projection<Id, E: Effect> {
+    /// Fold `envelope`'s `Effect`, and its ordering/correlation metadata, into read state.
+    fn project(&mut self, envelope: &EventEnvelope<Id, E>);
+}
+
+/// Queryable read state accumulated from `Effects` for a single `Actor`.
+pub trait View<E: Effect>: Default {
+    /// Fold `effect` into the view's state.
+    fn apply(&mut self, effect: &E);
+}
+
+/// In-memory `Projection` that keeps one `View` per aggregate id.
+pub struct InMemoryViewStore<Id, V> {
+    views: Mutex<HashMap<Id, V>>,
+}
+
+impl<Id, V> InMemoryViewStore<Id, V> {
+    /// Create an empty view store.
+    pub fn new() -> Self {
+        InMemoryViewStore {
+            views: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<Id, V> Default for InMemoryViewStore<Id, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Id, V> InMemoryViewStore<Id, V>
+where
+    Id: Eq + Hash,
+    V: Clone,
+{
+    /// Returns the current `View` for `actor_id`, if any `Effects` have been projected onto it.
+    pub fn view(&self, actor_id: &Id) -> Option<V> {
+        self.views.lock().unwrap().get(actor_id).cloned()
+    }
+}
+
+impl<Id, E, V> Projection<Id, E> for InMemoryViewStore<Id, V>
+where
+    Id: Eq + Hash + Clone,
+    E: Effect,
+    V: View<E>,
+{
+    fn project(&mut self, envelope: &EventEnvelope<Id, E>) {
+        let mut views = self.views.lock().unwrap();
+        let view = views.entry(envelope.actor_id.clone()).or_default();
+        view.apply(&envelope.effect);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct Opened {
+        key: u32,
+    }
+    impl Effect for Opened {
+        type Version = u8;
+        type Key = u32;
+        fn version(&self) -> Self::Version {
+            1
+        }
+        fn key(&self) -> Self::Key {
+            self.key
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct OpenCount {
+        count: u32,
+    }
+    impl View<Opened> for OpenCount {
+        fn apply(&mut self, _effect: &Opened) {
+            self.count += 1;
+        }
+    }
+
+    #[test]
+    fn project_accumulates_view_state_per_actor() {
+        let mut store: InMemoryViewStore<u32, OpenCount> = InMemoryViewStore::new();
+
+        store.project(&EventEnvelope::new(1, 0, Opened { key: 1 }));
+        store.project(&EventEnvelope::new(1, 1, Opened { key: 2 }));
+        store.project(&EventEnvelope::new(2, 0, Opened { key: 3 }));
+
+        assert_eq!(store.view(&1).unwrap().count, 2);
+        assert_eq!(store.view(&2).unwrap().count, 1);
+    }
+
+    #[test]
+    fn view_returns_none_for_unknown_actor() {
+        let store: InMemoryViewStore<u32, OpenCount> = InMemoryViewStore::new();
+
+        assert!(store.view(&99).is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! Snapshots of `Actor` state, keyed to the same generation counter an `EventStore` uses to
+//! sequence its `Effects`.
+//!
+//! Replaying an `Actor`'s entire history on every command is `O(history)`. A `Snapshot` lets a
+//! `SnapshottingDispatcher` load the last persisted state and replay only the `Effects`
+//! committed since, at the cost of the `Actor` tracking its own `generation`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+/// `Actor` state that can report the generation it is current as of.
+///
+/// `generation` must advance in step with the `EventStore`'s own generation counter: it is the
+/// number of `Effect` batches that have been applied to this `Actor`.
+pub trait Generational {
+    /// Generation this `Actor` instance reflects.
+    fn generation(&self) -> u64;
+}
+
+/// Persists and reloads `Actor` snapshots, keyed by aggregate id.
+pub trait SnapshotStore<Id, A> {
+    /// Error returned on load or save failure.
+    type Err: Error;
+    /// Load the most recently saved snapshot for `actor_id`, with the generation it was taken at.
+    fn load(&self, actor_id: &Id) -> Result<Option<(A, u64)>, Self::Err>;
+    /// Persist `actor` as the snapshot for `actor_id` at `generation`.
+    fn save(&self, actor_id: Id, actor: A, generation: u64) -> Result<(), Self::Err>;
+}
+
+/// Controls how often a `SnapshottingDispatcher` persists a new snapshot.
+pub struct SnapshotPolicy {
+    every: u64,
+}
+
+impl SnapshotPolicy {
+    /// Persist a new snapshot once at least `generations` have been committed since the last one.
+    pub fn every(generations: u64) -> Self {
+        SnapshotPolicy {
+            every: generations.max(1),
+        }
+    }
+
+    /// Returns whether a snapshot should be taken moving from `since_generation` to `generation`.
+    pub fn should_snapshot(&self, since_generation: u64, generation: u64) -> bool {
+        generation - since_generation >= self.every
+    }
+}
+
+/// `HashMap`-backed `SnapshotStore` suitable for tests or single-process use.
+pub struct InMemorySnapshotStore<Id, A> {
+    snapshots: Mutex<HashMap<Id, (A, u64)>>,
+}
+
+impl<Id, A> InMemorySnapshotStore<Id, A> {
+    /// Create an empty snapshot store.
+    pub fn new() -> Self {
+        InMemorySnapshotStore {
+            snapshots: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<Id, A> Default for InMemorySnapshotStore<Id, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Id, A> SnapshotStore<Id, A> for InMemorySnapshotStore<Id, A>
+where
+    Id: Eq + Hash,
+    A: Clone,
+{
+    type Err = std::convert::Infallible;
+
+    fn load(&self, actor_id: &Id) -> Result<Option<(A, u64)>, Self::Err> {
+        Ok(self.snapshots.lock().unwrap().get(actor_id).cloned())
+    }
+
+    fn save(&self, actor_id: Id, actor: A, generation: u64) -> Result<(), Self::Err> {
+        self.snapshots.lock().unwrap().insert(actor_id, (actor, generation));
+        Ok(())
+    }
+}
+
+impl<Id, A, T: SnapshotStore<Id, A>> SnapshotStore<Id, A> for std::sync::Arc<T> {
+    type Err = T::Err;
+
+    fn load(&self, actor_id: &Id) -> Result<Option<(A, u64)>, Self::Err> {
+        (**self).load(actor_id)
+    }
+
+    fn save(&self, actor_id: Id, actor: A, generation: u64) -> Result<(), Self::Err> {
+        (**self).save(actor_id, actor, generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_then_load_returns_the_latest_snapshot() {
+        let store: InMemorySnapshotStore<u32, String> = InMemorySnapshotStore::new();
+        store.save(1, String::from("first"), 3).unwrap();
+        store.save(1, String::from("second"), 7).unwrap();
+
+        let snapshot = store.load(&1).unwrap();
+
+        assert_eq!(snapshot, Some((String::from("second"), 7)));
+    }
+
+    #[test]
+    fn load_returns_none_for_unknown_actor() {
+        let store: InMemorySnapshotStore<u32, String> = InMemorySnapshotStore::new();
+        assert_eq!(store.load(&99).unwrap(), None);
+    }
+
+    #[test]
+    fn policy_snapshots_once_enough_generations_have_passed() {
+        let policy = SnapshotPolicy::every(5);
+
+        assert!(!policy.should_snapshot(0, 4));
+        assert!(policy.should_snapshot(0, 5));
+        assert!(policy.should_snapshot(2, 8));
+    }
+}
@@ -0,0 +1,303 @@
+//! Persistence for `Effects` produced by `Actors` handling `Causes`.
+//!
+//! Implementations guard writes with optimistic concurrency: an `append` must
+//! supply the `Actor` version it expects to find persisted, or the write is
+//! rejected with a `ConcurrencyError`. Persisted `Effects` are returned wrapped in an
+//! `EventEnvelope` carrying the ordering metadata the store assigned on commit.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Mutex;
+
+use crate::envelope::EventEnvelope;
+use crate::Effect;
+
+/// Returned by `EventStore::append` when `expected_version` does not match the persisted version.
+#[derive(Debug)]
+pub struct ConcurrencyError<Version: fmt::Debug> {
+    /// Version the caller expected to find persisted.
+    pub expected: Version,
+    /// Version actually found persisted.
+    pub found: Version,
+}
+
+impl<Version: fmt::Debug> fmt::Display for ConcurrencyError<Version> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected actor version {:?} but found {:?}",
+            self.expected, self.found
+        )
+    }
+}
+
+impl<Version: fmt::Debug> Error for ConcurrencyError<Version> {}
+
+/// Persists and reloads `Effects` produced by an `Actor` handling a `Cause`.
+pub trait EventStore<Id, E: Effect> {
+    /// Error returned on load or append failure.
+    type Err: Error;
+    /// Load every persisted `Effect` for `actor_id`, wrapped in its `EventEnvelope`, in the order
+    /// they were appended.
+    fn load(&self, actor_id: Id) -> Result<Vec<EventEnvelope<Id, E>>, Self::Err>;
+    /// Append `effects` for `actor_id`, enforcing optimistic concurrency against `expected_version`.
+    ///
+    /// Effects whose `Effect::key()` is already persisted are skipped for idempotency.
+    fn append(
+        &self,
+        actor_id: Id,
+        expected_version: E::Version,
+        effects: Vec<E>,
+    ) -> Result<(), Self::Err>;
+    /// Current generation persisted for `actor_id`, incremented once per committed `append` batch.
+    ///
+    /// `0` for an actor with no persisted history.
+    fn generation(&self, actor_id: Id) -> Result<u64, Self::Err>;
+    /// Load only the `EventEnvelopes` committed after `since_generation`, for use alongside a
+    /// `Snapshot`.
+    fn load_since(
+        &self,
+        actor_id: Id,
+        since_generation: u64,
+    ) -> Result<Vec<EventEnvelope<Id, E>>, Self::Err>;
+}
+
+/// Error produced by `InMemoryEventStore`.
+#[derive(Debug)]
+pub enum StoreError<Version: fmt::Debug> {
+    /// `expected_version` did not match the persisted version.
+    Concurrency(ConcurrencyError<Version>),
+}
+
+impl<Version: fmt::Debug> fmt::Display for StoreError<Version> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Concurrency(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<Version: fmt::Debug> Error for StoreError<Version> {}
+
+struct Entry<Id, E: Effect> {
+    version: E::Version,
+    generation: u64,
+    next_sequence: u64,
+    effects: Vec<EventEnvelope<Id, E>>,
+}
+
+/// `HashMap`-backed `EventStore` suitable for tests or single-process use.
+pub struct InMemoryEventStore<Id, E: Effect> {
+    log: Mutex<HashMap<Id, Entry<Id, E>>>,
+}
+
+impl<Id, E: Effect> InMemoryEventStore<Id, E> {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        InMemoryEventStore {
+            log: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<Id, E: Effect> Default for InMemoryEventStore<Id, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Id, E> EventStore<Id, E> for InMemoryEventStore<Id, E>
+where
+    Id: Eq + Hash + Clone,
+    E: Effect + Clone,
+    E::Version: Default + PartialEq + Clone + fmt::Debug,
+    E::Key: Eq + Hash,
+{
+    type Err = StoreError<E::Version>;
+
+    fn load(&self, actor_id: Id) -> Result<Vec<EventEnvelope<Id, E>>, Self::Err> {
+        let log = self.log.lock().unwrap();
+        Ok(log
+            .get(&actor_id)
+            .map(|entry| entry.effects.clone())
+            .unwrap_or_default())
+    }
+
+    fn append(
+        &self,
+        actor_id: Id,
+        expected_version: E::Version,
+        effects: Vec<E>,
+    ) -> Result<(), Self::Err> {
+        let mut log = self.log.lock().unwrap();
+        let entry = log.entry(actor_id.clone()).or_insert_with(|| Entry {
+            version: E::Version::default(),
+            generation: 0,
+            next_sequence: 0,
+            effects: Vec::new(),
+        });
+
+        if entry.version != expected_version {
+            return Err(StoreError::Concurrency(ConcurrencyError {
+                expected: expected_version,
+                found: entry.version.clone(),
+            }));
+        }
+
+        let persisted_keys: HashSet<E::Key> = entry
+            .effects
+            .iter()
+            .map(|envelope| envelope.effect.key())
+            .collect();
+        let next_generation = entry.generation + 1;
+        for effect in effects {
+            if persisted_keys.contains(&effect.key()) {
+                continue;
+            }
+            entry.version = effect.version();
+            entry.generation = next_generation;
+            let envelope = EventEnvelope::new(actor_id.clone(), entry.next_sequence, effect)
+                .with_generation(next_generation);
+            entry.next_sequence += 1;
+            entry.effects.push(envelope);
+        }
+
+        Ok(())
+    }
+
+    fn generation(&self, actor_id: Id) -> Result<u64, Self::Err> {
+        let log = self.log.lock().unwrap();
+        Ok(log.get(&actor_id).map(|entry| entry.generation).unwrap_or(0))
+    }
+
+    fn load_since(
+        &self,
+        actor_id: Id,
+        since_generation: u64,
+    ) -> Result<Vec<EventEnvelope<Id, E>>, Self::Err> {
+        let log = self.log.lock().unwrap();
+        Ok(log
+            .get(&actor_id)
+            .map(|entry| {
+                entry
+                    .effects
+                    .iter()
+                    .filter(|envelope| envelope.generation > since_generation)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Event {
+        version: u8,
+        key: u32,
+    }
+    impl Effect for Event {
+        type Version = u8;
+        type Key = u32;
+        fn version(&self) -> Self::Version {
+            self.version
+        }
+        fn key(&self) -> Self::Key {
+            self.key
+        }
+    }
+
+    #[test]
+    fn append_persists_effects_and_advances_version() {
+        let store: InMemoryEventStore<u32, Event> = InMemoryEventStore::new();
+        let result = store.append(1, 0, vec![Event { version: 1, key: 1 }]);
+
+        assert!(result.is_ok());
+        let effects = store.load(1).unwrap();
+        assert_eq!(effects.len(), 1);
+    }
+
+    #[test]
+    fn append_rejects_stale_expected_version() {
+        let store: InMemoryEventStore<u32, Event> = InMemoryEventStore::new();
+        store.append(1, 0, vec![Event { version: 1, key: 1 }]).unwrap();
+        let result = store.append(1, 0, vec![Event { version: 2, key: 2 }]);
+
+        assert!(result.is_err());
+        assert_eq!(store.load(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn append_skips_already_persisted_keys() {
+        let store: InMemoryEventStore<u32, Event> = InMemoryEventStore::new();
+        store.append(1, 0, vec![Event { version: 1, key: 1 }]).unwrap();
+        store
+            .append(1, 1, vec![Event { version: 1, key: 1 }, Event { version: 2, key: 2 }])
+            .unwrap();
+
+        assert_eq!(store.load(1).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn load_returns_empty_for_unknown_actor() {
+        let store: InMemoryEventStore<u32, Event> = InMemoryEventStore::new();
+        assert_eq!(store.load(99).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn generation_advances_once_per_append_batch() {
+        let store: InMemoryEventStore<u32, Event> = InMemoryEventStore::new();
+        assert_eq!(store.generation(1).unwrap(), 0);
+
+        store
+            .append(1, 0, vec![Event { version: 1, key: 1 }, Event { version: 1, key: 2 }])
+            .unwrap();
+        assert_eq!(store.generation(1).unwrap(), 1);
+
+        store.append(1, 1, vec![Event { version: 2, key: 3 }]).unwrap();
+        assert_eq!(store.generation(1).unwrap(), 2);
+    }
+
+    #[test]
+    fn load_since_returns_only_effects_after_generation() {
+        let store: InMemoryEventStore<u32, Event> = InMemoryEventStore::new();
+        store.append(1, 0, vec![Event { version: 1, key: 1 }]).unwrap();
+        store.append(1, 1, vec![Event { version: 2, key: 2 }]).unwrap();
+
+        let envelopes = store.load_since(1, 1).unwrap();
+
+        assert_eq!(envelopes.len(), 1);
+        assert_eq!(envelopes[0].effect.key, 2);
+    }
+
+    #[test]
+    fn load_returns_envelopes_tagged_with_their_commit_generation() {
+        let store: InMemoryEventStore<u32, Event> = InMemoryEventStore::new();
+        store.append(1, 0, vec![Event { version: 1, key: 1 }]).unwrap();
+        store.append(1, 1, vec![Event { version: 2, key: 2 }]).unwrap();
+
+        let envelopes = store.load(1).unwrap();
+
+        assert_eq!(envelopes[0].generation, 1);
+        assert_eq!(envelopes[1].generation, 2);
+    }
+
+    #[test]
+    fn load_returns_envelopes_with_actor_id_and_increasing_sequence() {
+        let store: InMemoryEventStore<u32, Event> = InMemoryEventStore::new();
+        store
+            .append(1, 0, vec![Event { version: 1, key: 1 }, Event { version: 1, key: 2 }])
+            .unwrap();
+
+        let envelopes = store.load(1).unwrap();
+
+        assert_eq!(envelopes[0].actor_id, 1);
+        assert_eq!(envelopes[0].sequence, 0);
+        assert_eq!(envelopes[1].sequence, 1);
+    }
+}